@@ -0,0 +1,92 @@
+use rayon::prelude::*;
+use std::error::Error;
+
+use super::CorrelationEngine;
+
+#[derive(Clone, Copy, Default)]
+struct Accumulator {
+    sum_x: f64,
+    sum_y: f64,
+    sum_xx: f64,
+    sum_yy: f64,
+    sum_xy: f64,
+}
+
+pub struct CpuCorrelationEngine {
+    accumulators: Vec<Accumulator>,
+    last_n: usize,
+    sample_duration: usize,
+    n_guesses: usize,
+}
+
+impl CpuCorrelationEngine {
+    pub fn new(sample_duration: usize, n_guesses: usize) -> Result<Self, Box<dyn Error>> {
+        let ret = CpuCorrelationEngine {
+            accumulators: vec![Accumulator::default(); n_guesses * sample_duration],
+            last_n: 0,
+            sample_duration,
+            n_guesses,
+        };
+
+        Ok(ret)
+    }
+}
+
+impl CorrelationEngine for CpuCorrelationEngine {
+    fn update(
+        &mut self,
+        samples: Vec<Vec<f64>>,
+        guesses: Vec<Vec<f64>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let n_samples = samples[0].len();
+        let sample_duration = self.sample_duration;
+
+        self.accumulators
+            .par_chunks_mut(sample_duration)
+            .zip(guesses.par_iter())
+            .for_each(|(row, guess)| {
+                for (acc, sample) in row.iter_mut().zip(samples.iter()) {
+                    for (&x, &y) in guess.iter().zip(sample.iter()) {
+                        acc.sum_x += x;
+                        acc.sum_y += y;
+                        acc.sum_xx += x * x;
+                        acc.sum_yy += y * y;
+                        acc.sum_xy += x * y;
+                    }
+                }
+            });
+
+        self.last_n += n_samples;
+
+        Ok(())
+    }
+
+    fn get_result(&self) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+        let n = self.last_n as f64;
+
+        let result: Vec<Vec<f64>> = self
+            .accumulators
+            .chunks(self.sample_duration)
+            .map(|row| {
+                row.iter()
+                    .map(|acc| {
+                        let numerator = n * acc.sum_xy - acc.sum_x * acc.sum_y;
+                        let denominator = ((n * acc.sum_xx - acc.sum_x * acc.sum_x)
+                            * (n * acc.sum_yy - acc.sum_y * acc.sum_y))
+                            .sqrt();
+
+                        if denominator == 0.0 {
+                            0.0
+                        } else {
+                            numerator / denominator
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+
+        debug_assert_eq!(result.len(), self.n_guesses);
+
+        Ok(result)
+    }
+}
@@ -0,0 +1,162 @@
+use rayon::prelude::*;
+use std::error::Error;
+
+use super::CorrelationEngine;
+
+pub struct MiaCorrelationEngine {
+    histograms: Vec<Vec<u64>>, // one row per guess, each row holds sample_duration histograms
+    n: u64,
+    l_bins: usize,
+    t_bins: usize,
+    l_min: f64,
+    l_max: f64,
+    t_min: f64,
+    t_max: f64,
+    sample_duration: usize,
+    n_guesses: usize,
+}
+
+impl MiaCorrelationEngine {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        sample_duration: usize,
+        n_guesses: usize,
+        l_bins: usize,
+        t_bins: usize,
+        l_min: f64,
+        l_max: f64,
+        t_min: f64,
+        t_max: f64,
+    ) -> Result<Self, Box<dyn Error>> {
+        if l_bins == 0 {
+            return Err("l_bins must be greater than 0".into());
+        }
+        if t_bins == 0 {
+            return Err("t_bins must be greater than 0".into());
+        }
+        if l_max <= l_min {
+            return Err("l_max must be greater than l_min".into());
+        }
+        if t_max <= t_min {
+            return Err("t_max must be greater than t_min".into());
+        }
+
+        let ret = MiaCorrelationEngine {
+            histograms: vec![vec![0u64; l_bins * t_bins]; n_guesses * sample_duration],
+            n: 0,
+            l_bins,
+            t_bins,
+            l_min,
+            l_max,
+            t_min,
+            t_max,
+            sample_duration,
+            n_guesses,
+        };
+
+        Ok(ret)
+    }
+
+    // Equal-width binning over [l_min, l_max], mirroring quantize_t below. The range
+    // must be passed by the caller since the leakage model's output scale varies
+    // (Hamming weight 0..8, dec-table popcount 0..32, an unbounded stochastic fit...).
+    fn quantize_l(&self, l: f64) -> usize {
+        let width = (self.l_max - self.l_min) / self.l_bins as f64;
+        let bin = ((l - self.l_min) / width).floor().max(0.0) as usize;
+        bin.min(self.l_bins - 1)
+    }
+
+    fn quantize_t(&self, t: f64) -> usize {
+        let width = (self.t_max - self.t_min) / self.t_bins as f64;
+        let bin = ((t - self.t_min) / width).floor().max(0.0) as usize;
+        bin.min(self.t_bins - 1)
+    }
+}
+
+impl CorrelationEngine for MiaCorrelationEngine {
+    fn update(
+        &mut self,
+        samples: Vec<Vec<f64>>,
+        guesses: Vec<Vec<f64>>,
+    ) -> Result<(), Box<dyn Error>> {
+        let n_samples = samples[0].len();
+        let sample_duration = self.sample_duration;
+        let t_bins = self.t_bins;
+
+        // Quantize every (guess, trace) leakage estimate and every (sample, trace)
+        // measurement once, up front, so the parallel accumulation below only does
+        // histogram bumps.
+        let l_bin_table: Vec<Vec<usize>> = guesses
+            .iter()
+            .map(|guess| guess.iter().map(|&l| self.quantize_l(l)).collect())
+            .collect();
+        let t_bin_table: Vec<Vec<usize>> = samples
+            .iter()
+            .map(|sample| sample.iter().map(|&t| self.quantize_t(t)).collect())
+            .collect();
+
+        self.histograms
+            .par_chunks_mut(sample_duration)
+            .zip(l_bin_table.par_iter())
+            .for_each(|(row, l_bins_for_guess)| {
+                for (histogram, t_bins_for_sample) in row.iter_mut().zip(t_bin_table.iter()) {
+                    for (&l_bin, &t_bin) in l_bins_for_guess.iter().zip(t_bins_for_sample.iter()) {
+                        histogram[l_bin * t_bins + t_bin] += 1;
+                    }
+                }
+            });
+
+        self.n += n_samples as u64;
+
+        Ok(())
+    }
+
+    fn get_result(&self) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+        let n = self.n as f64;
+        let l_bins = self.l_bins;
+        let t_bins = self.t_bins;
+
+        let result: Vec<Vec<f64>> = self
+            .histograms
+            .chunks(self.sample_duration)
+            .map(|row| {
+                row.iter()
+                    .map(|histogram| mutual_information(histogram, l_bins, t_bins, n))
+                    .collect()
+            })
+            .collect();
+
+        debug_assert_eq!(result.len(), self.n_guesses);
+
+        Ok(result)
+    }
+}
+
+fn mutual_information(histogram: &[u64], l_bins: usize, t_bins: usize, n: f64) -> f64 {
+    if n == 0.0 {
+        return 0.0;
+    }
+
+    let mut p_l = vec![0.0; l_bins];
+    let mut p_t = vec![0.0; t_bins];
+
+    for l in 0..l_bins {
+        for t in 0..t_bins {
+            let p = histogram[l * t_bins + t] as f64 / n;
+            p_l[l] += p;
+            p_t[t] += p;
+        }
+    }
+
+    let mut mi = 0.0;
+    for l in 0..l_bins {
+        for t in 0..t_bins {
+            let p_lt = histogram[l * t_bins + t] as f64 / n;
+            if p_lt > 0.0 && p_l[l] > 0.0 && p_t[t] > 0.0 {
+                mi += p_lt * (p_lt / (p_l[l] * p_t[t])).log2();
+            }
+        }
+    }
+
+    mi
+}
@@ -0,0 +1,52 @@
+use std::error::Error;
+
+mod cpu;
+mod mia;
+mod opencl;
+
+pub use cpu::CpuCorrelationEngine;
+pub use mia::MiaCorrelationEngine;
+pub use opencl::OpenclCorrelationEngine;
+
+pub trait CorrelationEngine: Send {
+    fn update(
+        &mut self,
+        samples: Vec<Vec<f64>>,
+        guesses: Vec<Vec<f64>>,
+    ) -> Result<(), Box<dyn Error>>;
+    fn get_result(&self) -> Result<Vec<Vec<f64>>, Box<dyn Error>>;
+}
+
+// Auto tries OpenCL first, falling back to the CPU engine if no device is available
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CorrelationEngineBackend {
+    Auto,
+    Opencl,
+    Cpu,
+}
+
+pub fn new_correlation_engine(
+    backend: CorrelationEngineBackend,
+    sample_duration: usize,
+    n_guesses: usize,
+) -> Result<Box<dyn CorrelationEngine>, Box<dyn Error>> {
+    match backend {
+        CorrelationEngineBackend::Opencl => Ok(Box::new(OpenclCorrelationEngine::new(
+            sample_duration,
+            n_guesses,
+        )?)),
+        CorrelationEngineBackend::Cpu => Ok(Box::new(CpuCorrelationEngine::new(
+            sample_duration,
+            n_guesses,
+        )?)),
+        CorrelationEngineBackend::Auto => {
+            match OpenclCorrelationEngine::new(sample_duration, n_guesses) {
+                Ok(engine) => Ok(Box::new(engine)),
+                Err(_) => Ok(Box::new(CpuCorrelationEngine::new(
+                    sample_duration,
+                    n_guesses,
+                )?)),
+            }
+        }
+    }
+}
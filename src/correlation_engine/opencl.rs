@@ -1,6 +1,8 @@
 use ocl::{Buffer, MemFlags, ProQue};
 use std::error::Error;
 
+use super::CorrelationEngine;
+
 pub struct OpenclCorrelationEngine {
     pro_queue: ProQue,
     kernel: ocl::Kernel,
@@ -12,7 +14,7 @@ pub struct OpenclCorrelationEngine {
 
 impl OpenclCorrelationEngine {
     pub fn new(sample_duration: usize, n_guesses: usize) -> Result<Self, Box<dyn Error>> {
-        let src = include_str!("correlation.cl");
+        let src = include_str!("../correlation.cl");
 
         let pro_queue = ProQue::builder()
             .src(src)
@@ -59,8 +61,10 @@ impl OpenclCorrelationEngine {
 
         Ok(ret)
     }
+}
 
-    pub fn update(
+impl CorrelationEngine for OpenclCorrelationEngine {
+    fn update(
         &mut self,
         samples: Vec<Vec<f64>>,
         guesses: Vec<Vec<f64>>,
@@ -110,7 +114,7 @@ impl OpenclCorrelationEngine {
         Ok(())
     }
 
-    pub fn get_result(&self) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
+    fn get_result(&self) -> Result<Vec<Vec<f64>>, Box<dyn Error>> {
         let mut result = vec![0.0f64; self.result_buffer.len()];
         self.result_buffer.read(&mut result).enq()?;
 
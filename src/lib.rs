@@ -1,28 +1,47 @@
-use numpy::{PyArray2, PyReadonlyArray2};
+use numpy::{PyArray1, PyArray2, PyReadonlyArray2};
 use pyo3::{
     exceptions::PyTypeError,
     prelude::*,
     types::{PyBytes, PyDict},
+    wrap_pyfunction,
 };
 
 mod aes;
 mod correlation_engine;
 mod power_consumption_models;
+mod tvla;
 
-use correlation_engine::OpenclCorrelationEngine;
+use correlation_engine::{
+    new_correlation_engine, CorrelationEngine, CorrelationEngineBackend, MiaCorrelationEngine,
+};
 use power_consumption_models::{
-    state_hamming_weight, ConsumptionModelRound0, ConsumptionModelRound0DecTable,
-    ConsumptionModelRound1, ConsumptionModelRound1DecTable, ConsumptionModelTrait,
+    solve_9x9, state_hamming_weight, ConsumptionModelRound0, ConsumptionModelRound0DecTable,
+    ConsumptionModelRound1, ConsumptionModelRound1DecTable, ConsumptionModelStochastic,
+    ConsumptionModelTrait,
 };
+use tvla::TvlaEngine;
 
 #[pyclass]
 struct CpaSolver {
     // Ciphertext inputs
-    correlation_engine: Option<OpenclCorrelationEngine>,
+    correlation_engine: Option<Box<dyn CorrelationEngine>>,
+    correlation_engine_backend: CorrelationEngineBackend,
     power_consumption_model: Box<dyn ConsumptionModelTrait>,
     k_index: usize,
 }
 
+fn get_correlation_engine_backend(backend: Option<&str>) -> PyResult<CorrelationEngineBackend> {
+    match backend {
+        None => Ok(CorrelationEngineBackend::Auto),
+        Some("auto") => Ok(CorrelationEngineBackend::Auto),
+        Some("opencl") => Ok(CorrelationEngineBackend::Opencl),
+        Some("cpu") => Ok(CorrelationEngineBackend::Cpu),
+        Some(_) => Err(PyErr::new::<PyTypeError, _>(
+            "Unknown correlation engine backend, expected one of \"auto\", \"opencl\", \"cpu\"",
+        )),
+    }
+}
+
 fn get_power_consumption_model(
     name: &str,
     py_kwargs: Option<&PyDict>,
@@ -93,11 +112,85 @@ fn get_power_consumption_model(
             tk0,
             beta_modifier,
         )))
+    } else if name == "stochastic" {
+        let Some(args) = py_kwargs else
+            {
+                return Err(PyErr::new::<PyTypeError, _>("Missing argument beta"))
+            };
+
+        let Some(beta) = args.get_item("beta") else
+            {
+                return Err(PyErr::new::<PyTypeError, _>("Missing argument beta"))
+            };
+
+        let Ok(beta) = beta.extract::<Vec<f64>>() else
+            {
+                return Err(PyErr::new::<PyTypeError, _>("beta has invalid type"))
+            };
+
+        if beta.len() != 9 {
+            return Err(PyErr::new::<PyTypeError, _>(
+                "beta must have 9 coefficients",
+            ));
+        }
+
+        let beta: [f64; 9] = beta.try_into().unwrap();
+
+        Ok(Box::new(ConsumptionModelStochastic::new(beta)))
     } else {
         return Err(PyErr::new::<PyTypeError, _>("Unknown model name"));
     }
 }
 
+#[pyfunction]
+fn profile_stochastic_model(
+    payloads: Vec<[u8; 16]>,
+    samples: Vec<f64>,
+    key: [u8; 16],
+    k_index: usize,
+) -> PyResult<Vec<f64>> {
+    if payloads.len() != samples.len() {
+        return Err(PyErr::new::<PyTypeError, _>(
+            "payloads and samples must have the same length",
+        ));
+    }
+
+    // Build the 9-column design matrix: a constant term plus the 8 bits of the
+    // predicted S-box output.
+    let design: Vec<[f64; 9]> = payloads
+        .iter()
+        .map(|payload| {
+            let v = aes::sbox(payload[k_index] ^ key[k_index]);
+            let mut row = [0.0; 9];
+            row[0] = 1.0;
+            for i in 0..8 {
+                row[i + 1] = ((v >> i) & 1) as f64;
+            }
+            row
+        })
+        .collect();
+
+    // Solve the normal equations X^T X beta = X^T y
+    let mut xtx = [[0.0f64; 9]; 9];
+    let mut xty = [0.0f64; 9];
+
+    for (row, &y) in design.iter().zip(samples.iter()) {
+        for i in 0..9 {
+            xty[i] += row[i] * y;
+            for j in 0..9 {
+                xtx[i][j] += row[i] * row[j];
+            }
+        }
+    }
+
+    match solve_9x9(xtx, xty) {
+        Some(beta) => Ok(beta.to_vec()),
+        None => Err(PyErr::new::<PyTypeError, _>(
+            "Singular design matrix, cannot fit model",
+        )),
+    }
+}
+
 #[pymethods]
 impl CpaSolver {
     #[new]
@@ -106,11 +199,14 @@ impl CpaSolver {
         k_index: usize,
         beta_modifier: f64,
         py_kwargs: Option<&PyDict>,
+        backend: Option<&str>,
     ) -> PyResult<Self> {
         let power_consumption_model = get_power_consumption_model(name, py_kwargs, beta_modifier)?;
+        let correlation_engine_backend = get_correlation_engine_backend(backend)?;
 
         let ret = CpaSolver {
             correlation_engine: None,
+            correlation_engine_backend,
             power_consumption_model,
             k_index,
         };
@@ -125,13 +221,14 @@ impl CpaSolver {
         // Instantiate a correlation engine if needed
         if self.correlation_engine.is_none() {
             let duration = py_samples.shape()[1];
-            let correlation_engine = match OpenclCorrelationEngine::new(duration, 256) {
-                Ok(engine) => engine,
-                Err(e) => {
-                    let msg = format!("Cannot build correlation engine: {:?}", e);
-                    return Err(PyErr::new::<PyTypeError, _>(msg));
-                }
-            };
+            let correlation_engine =
+                match new_correlation_engine(self.correlation_engine_backend, duration, 256) {
+                    Ok(engine) => engine,
+                    Err(e) => {
+                        let msg = format!("Cannot build correlation engine: {:?}", e);
+                        return Err(PyErr::new::<PyTypeError, _>(msg));
+                    }
+                };
             self.correlation_engine = Some(correlation_engine);
         }
 
@@ -185,18 +282,135 @@ impl CpaSolver {
     }
 }
 
+#[pyclass]
+struct FullKeyCpaSolver {
+    correlation_engine: Option<Box<dyn CorrelationEngine>>,
+    correlation_engine_backend: CorrelationEngineBackend,
+    power_consumption_model: Box<dyn ConsumptionModelTrait>,
+}
+
+#[pymethods]
+impl FullKeyCpaSolver {
+    #[new]
+    fn new(
+        name: &str,
+        beta_modifier: f64,
+        py_kwargs: Option<&PyDict>,
+        backend: Option<&str>,
+    ) -> PyResult<Self> {
+        let power_consumption_model = get_power_consumption_model(name, py_kwargs, beta_modifier)?;
+        let correlation_engine_backend = get_correlation_engine_backend(backend)?;
+
+        let ret = FullKeyCpaSolver {
+            correlation_engine: None,
+            correlation_engine_backend,
+            power_consumption_model,
+        };
+        Ok(ret)
+    }
+
+    fn update(
+        &mut self,
+        payloads: Vec<[u8; 16]>,
+        py_samples: PyReadonlyArray2<f64>,
+    ) -> PyResult<()> {
+        // Instantiate a correlation engine if needed
+        if self.correlation_engine.is_none() {
+            let duration = py_samples.shape()[1];
+            let correlation_engine =
+                match new_correlation_engine(self.correlation_engine_backend, duration, 16 * 256) {
+                    Ok(engine) => engine,
+                    Err(e) => {
+                        let msg = format!("Cannot build correlation engine: {:?}", e);
+                        return Err(PyErr::new::<PyTypeError, _>(msg));
+                    }
+                };
+            self.correlation_engine = Some(correlation_engine);
+        }
+
+        // Generate guesses for all 256 candidates at every one of the 16 key indices
+        let guesses: Vec<Vec<f64>> = (0..16)
+            .flat_map(|k_index| {
+                (0..=u8::MAX).map(move |guess| {
+                    payloads
+                        .iter()
+                        .map(|c| self.power_consumption_model.estimate(c, guess, k_index))
+                        .collect()
+                })
+            })
+            .collect();
+
+        let mut samples: Vec<Vec<f64>> = Vec::new();
+        let py_samples = py_samples.as_array();
+
+        for column in py_samples.columns() {
+            let v = column.to_vec();
+            samples.push(v);
+        }
+
+        let correlation_engine = self.correlation_engine.as_mut().unwrap();
+        match correlation_engine.update(samples, guesses) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let msg = format!("Cannot update correlation engine: {:?}", e);
+                Err(PyErr::new::<PyTypeError, _>(msg))
+            }
+        }
+    }
+
+    // Reduces each (k_index, guess) row to its best-correlation peak: (16, 256)
+    fn get_result(&self) -> PyResult<Py<PyArray2<f64>>> {
+        if self.correlation_engine.is_none() {
+            return Err(PyErr::new::<PyTypeError, _>("No results"));
+        }
+        let correlation_engine = self.correlation_engine.as_ref().unwrap();
+        let result = match correlation_engine.get_result() {
+            Ok(result) => result,
+            Err(e) => {
+                let msg = format!("Cannot get correlation results: {:?}", e);
+                return Err(PyErr::new::<PyTypeError, _>(msg));
+            }
+        };
+
+        let peaks: Vec<Vec<f64>> = result
+            .chunks(256)
+            .map(|k_index_rows| {
+                k_index_rows
+                    .iter()
+                    .map(|row| {
+                        row.iter()
+                            .cloned()
+                            .fold(0.0, |a: f64, b| if b.abs() > a.abs() { b } else { a })
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let ret = Python::with_gil(|py| -> Py<PyArray2<f64>> {
+            let test = PyArray2::from_vec2(py, &peaks).unwrap();
+            test.to_owned()
+        });
+
+        Ok(ret)
+    }
+}
+
 #[pyclass]
 struct AssessmentSolver {
-    correlation_engine: Option<OpenclCorrelationEngine>,
+    correlation_engine: Option<Box<dyn CorrelationEngine>>,
+    correlation_engine_backend: CorrelationEngineBackend,
     keys: Vec<[u8; 16]>,
 }
 
 #[pymethods]
 impl AssessmentSolver {
     #[new]
-    fn new(keys: Vec<[u8; 16]>) -> PyResult<Self> {
+    fn new(keys: Vec<[u8; 16]>, backend: Option<&str>) -> PyResult<Self> {
+        let correlation_engine_backend = get_correlation_engine_backend(backend)?;
+
         let ret = AssessmentSolver {
             correlation_engine: None,
+            correlation_engine_backend,
             keys,
         };
         Ok(ret)
@@ -214,14 +428,17 @@ impl AssessmentSolver {
             // Check length of AES states power vector
             let dummy_payload = [0u8; 16];
             let dummy_states = aes::compute_all_states(&dummy_payload, &self.keys);
-            let correlation_engine =
-                match OpenclCorrelationEngine::new(duration, dummy_states.len()) {
-                    Ok(engine) => engine,
-                    Err(e) => {
-                        let msg = format!("Cannot build correlation engine: {:?}", e);
-                        return Err(PyErr::new::<PyTypeError, _>(msg));
-                    }
-                };
+            let correlation_engine = match new_correlation_engine(
+                self.correlation_engine_backend,
+                duration,
+                dummy_states.len(),
+            ) {
+                Ok(engine) => engine,
+                Err(e) => {
+                    let msg = format!("Cannot build correlation engine: {:?}", e);
+                    return Err(PyErr::new::<PyTypeError, _>(msg));
+                }
+            };
             self.correlation_engine = Some(correlation_engine);
         }
 
@@ -285,10 +502,199 @@ impl AssessmentSolver {
     }
 }
 
+#[pyclass]
+struct TvlaSolver {
+    engine: Option<TvlaEngine>,
+}
+
+#[pymethods]
+impl TvlaSolver {
+    #[new]
+    fn new() -> PyResult<Self> {
+        Ok(TvlaSolver { engine: None })
+    }
+
+    fn update(&mut self, groups: Vec<u8>, py_samples: PyReadonlyArray2<f64>) -> PyResult<()> {
+        if groups.len() != py_samples.shape()[0] {
+            return Err(PyErr::new::<PyTypeError, _>(
+                "groups must have one entry per trace",
+            ));
+        }
+
+        for &group in &groups {
+            if group > 1 {
+                return Err(PyErr::new::<PyTypeError, _>(
+                    "group must be 0 (fixed input) or 1 (random input)",
+                ));
+            }
+        }
+
+        // Instantiate the engine if needed
+        if self.engine.is_none() {
+            let duration = py_samples.shape()[1];
+            self.engine = Some(TvlaEngine::new(duration));
+        }
+
+        let mut samples: Vec<Vec<f64>> = Vec::new();
+        let py_samples = py_samples.as_array();
+
+        for column in py_samples.columns() {
+            let v = column.to_vec();
+            samples.push(v);
+        }
+
+        let engine = self.engine.as_mut().unwrap();
+        engine.update(samples, groups);
+
+        Ok(())
+    }
+
+    fn get_result(&self) -> PyResult<Py<PyArray1<f64>>> {
+        if self.engine.is_none() {
+            return Err(PyErr::new::<PyTypeError, _>("No results"));
+        }
+        let engine = self.engine.as_ref().unwrap();
+        let result = engine.get_result();
+
+        let ret = Python::with_gil(|py| -> Py<PyArray1<f64>> {
+            let test = PyArray1::from_vec(py, result);
+            test.to_owned()
+        });
+
+        Ok(ret)
+    }
+}
+
+#[pyclass]
+struct MiaSolver {
+    correlation_engine: Option<MiaCorrelationEngine>,
+    power_consumption_model: Box<dyn ConsumptionModelTrait>,
+    k_index: usize,
+    l_bins: usize,
+    t_bins: usize,
+    l_min: f64,
+    l_max: f64,
+    t_min: f64,
+    t_max: f64,
+}
+
+#[pymethods]
+impl MiaSolver {
+    #[new]
+    #[allow(clippy::too_many_arguments)]
+    fn new(
+        name: &str,
+        k_index: usize,
+        beta_modifier: f64,
+        l_min: f64,
+        l_max: f64,
+        t_min: f64,
+        t_max: f64,
+        l_bins: Option<usize>,
+        t_bins: Option<usize>,
+        py_kwargs: Option<&PyDict>,
+    ) -> PyResult<Self> {
+        let power_consumption_model = get_power_consumption_model(name, py_kwargs, beta_modifier)?;
+
+        let ret = MiaSolver {
+            correlation_engine: None,
+            power_consumption_model,
+            k_index,
+            l_bins: l_bins.unwrap_or(9),
+            t_bins: t_bins.unwrap_or(16),
+            l_min,
+            l_max,
+            t_min,
+            t_max,
+        };
+        Ok(ret)
+    }
+
+    fn update(
+        &mut self,
+        payloads: Vec<[u8; 16]>,
+        py_samples: PyReadonlyArray2<f64>,
+    ) -> PyResult<()> {
+        // Instantiate a correlation engine if needed
+        if self.correlation_engine.is_none() {
+            let duration = py_samples.shape()[1];
+            let correlation_engine = match MiaCorrelationEngine::new(
+                duration,
+                256,
+                self.l_bins,
+                self.t_bins,
+                self.l_min,
+                self.l_max,
+                self.t_min,
+                self.t_max,
+            ) {
+                Ok(engine) => engine,
+                Err(e) => {
+                    let msg = format!("Cannot build correlation engine: {:?}", e);
+                    return Err(PyErr::new::<PyTypeError, _>(msg));
+                }
+            };
+            self.correlation_engine = Some(correlation_engine);
+        }
+
+        // Generated guesses for all possible bytes
+        let guesses: Vec<Vec<f64>> = (0..=u8::MAX)
+            .map(|i| {
+                payloads
+                    .iter()
+                    .map(|c| self.power_consumption_model.estimate(c, i, self.k_index))
+                    .collect()
+            })
+            .collect();
+
+        let mut samples: Vec<Vec<f64>> = Vec::new();
+        let py_samples = py_samples.as_array();
+
+        for column in py_samples.columns() {
+            let v = column.to_vec();
+            samples.push(v);
+        }
+
+        let correlation_engine = self.correlation_engine.as_mut().unwrap();
+        match correlation_engine.update(samples, guesses) {
+            Ok(_) => Ok(()),
+            Err(e) => {
+                let msg = format!("Cannot update correlation engine: {:?}", e);
+                Err(PyErr::new::<PyTypeError, _>(msg))
+            }
+        }
+    }
+
+    fn get_result(&self) -> PyResult<Py<PyArray2<f64>>> {
+        if self.correlation_engine.is_none() {
+            return Err(PyErr::new::<PyTypeError, _>("No results"));
+        }
+        let correlation_engine = self.correlation_engine.as_ref().unwrap();
+        let result = match correlation_engine.get_result() {
+            Ok(result) => result,
+            Err(e) => {
+                let msg = format!("Cannot get correlation results: {:?}", e);
+                return Err(PyErr::new::<PyTypeError, _>(msg));
+            }
+        };
+
+        let ret = Python::with_gil(|py| -> Py<PyArray2<f64>> {
+            let test = PyArray2::from_vec2(py, &result).unwrap();
+            test.to_owned()
+        });
+
+        Ok(ret)
+    }
+}
+
 #[pymodule]
 fn cpa_lib(_py: Python, m: &PyModule) -> PyResult<()> {
     m.add_class::<CpaSolver>()?;
+    m.add_class::<FullKeyCpaSolver>()?;
     m.add_class::<AssessmentSolver>()?;
+    m.add_class::<TvlaSolver>()?;
+    m.add_class::<MiaSolver>()?;
+    m.add_function(wrap_pyfunction!(profile_stochastic_model, m)?)?;
 
     Ok(())
 }
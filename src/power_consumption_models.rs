@@ -22,6 +22,10 @@ pub struct ConsumptionModelRound1DecTable {
     tk0: [u8; 16], // Tweaked key of the first round
 }
 
+pub struct ConsumptionModelStochastic {
+    beta: [f64; 9], // beta[0] is the constant term, beta[1..=8] are the per-bit terms
+}
+
 impl ConsumptionModelTrait for ConsumptionModelRound0 {
     fn estimate(&self, payload: &[u8; 16], guess: u8, index: usize) -> f64 {
         let p = aes::sbox(payload[index] ^ guess).count_ones() as f64;
@@ -94,6 +98,21 @@ impl ConsumptionModelRound1 {
     }
 }
 
+impl ConsumptionModelTrait for ConsumptionModelStochastic {
+    fn estimate(&self, payload: &[u8; 16], guess: u8, index: usize) -> f64 {
+        let v = aes::sbox(payload[index] ^ guess);
+
+        let mut l = self.beta[0];
+        for i in 0..8 {
+            if (v >> i) & 1 == 1 {
+                l += self.beta[i + 1];
+            }
+        }
+
+        l
+    }
+}
+
 impl ConsumptionModelRound0DecTable {
     pub fn new(beta_modifier: f64) -> Self {
         ConsumptionModelRound0DecTable { beta_modifier }
@@ -109,6 +128,42 @@ impl ConsumptionModelRound1DecTable {
     }
 }
 
+impl ConsumptionModelStochastic {
+    pub fn new(beta: [f64; 9]) -> Self {
+        ConsumptionModelStochastic { beta }
+    }
+}
+
 pub fn state_hamming_weight(state: &[u8; 16]) -> f64 {
     state.iter().map(|c| c.count_ones() as f64).sum()
 }
+
+// Solves a * x = b by Gaussian elimination with partial pivoting, None if singular
+pub(crate) fn solve_9x9(mut a: [[f64; 9]; 9], mut b: [f64; 9]) -> Option<[f64; 9]> {
+    for col in 0..9 {
+        let pivot_row = (col..9).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+
+        if a[pivot_row][col].abs() < 1e-12 {
+            return None;
+        }
+
+        a.swap(col, pivot_row);
+        b.swap(col, pivot_row);
+
+        for row in (col + 1)..9 {
+            let factor = a[row][col] / a[col][col];
+            for k in col..9 {
+                a[row][k] -= factor * a[col][k];
+            }
+            b[row] -= factor * b[col];
+        }
+    }
+
+    let mut x = [0.0; 9];
+    for row in (0..9).rev() {
+        let sum: f64 = (row + 1..9).map(|k| a[row][k] * x[k]).sum();
+        x[row] = (b[row] - sum) / a[row][row];
+    }
+
+    Some(x)
+}
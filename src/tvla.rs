@@ -0,0 +1,63 @@
+#[derive(Clone, Copy, Default)]
+struct GroupAccumulator {
+    n: usize,
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl GroupAccumulator {
+    fn push(&mut self, value: f64) {
+        self.n += 1;
+        self.sum += value;
+        self.sum_sq += value * value;
+    }
+
+    fn mean(&self) -> f64 {
+        self.sum / self.n as f64
+    }
+
+    fn variance(&self) -> f64 {
+        self.sum_sq / self.n as f64 - self.mean().powi(2)
+    }
+}
+
+pub struct TvlaEngine {
+    groups: Vec<[GroupAccumulator; 2]>,
+}
+
+impl TvlaEngine {
+    pub fn new(sample_duration: usize) -> Self {
+        TvlaEngine {
+            groups: vec![[GroupAccumulator::default(); 2]; sample_duration],
+        }
+    }
+
+    pub fn update(&mut self, samples: Vec<Vec<f64>>, group: Vec<u8>) {
+        for (accumulators, sample) in self.groups.iter_mut().zip(samples.iter()) {
+            for (&value, &g) in sample.iter().zip(group.iter()) {
+                accumulators[g as usize].push(value);
+            }
+        }
+    }
+
+    pub fn get_result(&self) -> Vec<f64> {
+        self.groups
+            .iter()
+            .map(|[g0, g1]| {
+                if g0.n == 0 || g1.n == 0 {
+                    return 0.0;
+                }
+
+                let numerator = g0.mean() - g1.mean();
+                let denominator =
+                    (g0.variance() / g0.n as f64 + g1.variance() / g1.n as f64).sqrt();
+
+                if denominator == 0.0 {
+                    0.0
+                } else {
+                    numerator / denominator
+                }
+            })
+            .collect()
+    }
+}